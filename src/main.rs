@@ -1,7 +1,7 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use colored::*;
 use std::fs::{self};
-use std::io::{self, BufRead};
+use std::io::{self};
 use std::path::{Path, PathBuf};
 use std::process::{Command, exit};
 use nix::unistd::Uid;
@@ -10,33 +10,103 @@ use nix::unistd::Uid;
 #[command(name = "BtRust")]
 #[command(about = "A tool to recover files from Btrfs file systems")]
 #[command(long_about = "BtRust is designed to facilitate file recovery from Btrfs file systems. \
-    It allows users to list recoverable files, perform dry-run recovery, and restore files using regex patterns or explicit paths.")]
-struct Args {
+    It allows users to list recoverable files, perform dry-run recovery, mount a device for \
+    manual inspection, build a catalog of recoverable files, or restore files using regex \
+    patterns or explicit paths.")]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// List all recoverable files at the specified depth without recovering
+    List(ListArgs),
+    /// Recover files matching the given paths or regex patterns
+    Recover(RecoverArgs),
+    /// Show which files would be recovered without actually recovering them
+    DryRun(DryRunArgs),
+    /// Mount a Btrfs device read-only for manual inspection
+    Mount(MountArgs),
+    /// Build a catalog of recoverable files and write it to a file
+    Catalog(CatalogArgs),
+}
+
+#[derive(Args)]
+struct DeviceArgs {
     /// Specify the Btrfs device to perform recovery on
     #[arg(short = 'd', long = "device", required = true)]
     device: String,
+}
+
+#[derive(Args)]
+struct ListArgs {
+    #[command(flatten)]
+    device: DeviceArgs,
+
+    /// Set depth level of the search (0-2, default: 0)
+    #[arg(short = 'l', long = "level", default_value = "0")]
+    #[arg(value_parser = clap::value_parser!(u8).range(0..=2))]
+    level: u8,
+}
+
+#[derive(Args)]
+struct RecoverArgs {
+    #[command(flatten)]
+    device: DeviceArgs,
 
     /// Specify the output directory where recovered files will be stored (use . for current directory)
     #[arg(short = 'o', long = "output", required = true)]
     output: PathBuf,
 
     /// Specify paths or regex patterns for files to recover (use ".*" to recover all files)
-    #[arg(short = 'p', long = "path")]
+    #[arg(short = 'p', long = "path", required = true)]
     paths: Vec<String>,
 
     /// Set depth level of recovery (0-2, default: 0)
     #[arg(short = 'l', long = "level", default_value = "0")]
     #[arg(value_parser = clap::value_parser!(u8).range(0..=2))]
     level: u8,
+}
+
+#[derive(Args)]
+struct DryRunArgs {
+    #[command(flatten)]
+    device: DeviceArgs,
+
+    /// Specify paths or regex patterns for files to recover (use ".*" to recover all files)
+    #[arg(short = 'p', long = "path", required = true)]
+    paths: Vec<String>,
 
-    /// List all recoverable files at specified depth without recovering
-    #[arg(short = 'L', long = "list")]
+    /// Set depth level of the search (0-2, default: 0)
+    #[arg(short = 'l', long = "level", default_value = "0")]
     #[arg(value_parser = clap::value_parser!(u8).range(0..=2))]
-    list: Option<u8>,
+    level: u8,
+}
 
-    /// Perform a dry run without actual recovery
-    #[arg(short = 'n', long = "dry-run")]
-    dry_run: bool,
+#[derive(Args)]
+struct MountArgs {
+    #[command(flatten)]
+    device: DeviceArgs,
+
+    /// Specify the mountpoint to mount the device at (use . for current directory)
+    #[arg(short = 'o', long = "output", required = true)]
+    mountpoint: PathBuf,
+}
+
+#[derive(Args)]
+struct CatalogArgs {
+    #[command(flatten)]
+    device: DeviceArgs,
+
+    /// Specify the output file where the catalog of recoverable files will be written
+    #[arg(short = 'o', long = "output", required = true)]
+    output: PathBuf,
+
+    /// Set depth level of the search (0-2, default: 0)
+    #[arg(short = 'l', long = "level", default_value = "0")]
+    #[arg(value_parser = clap::value_parser!(u8).range(0..=2))]
+    level: u8,
 }
 
 struct RecoveryContext {
@@ -53,6 +123,16 @@ impl RecoveryContext {
             regex: String::new(),
         }
     }
+
+    fn cleanup(&self) -> io::Result<()> {
+        if Path::new(&self.roots_file).exists() {
+            fs::remove_file(&self.roots_file)?;
+        }
+        if Path::new(&self.tmp_file).exists() {
+            fs::remove_file(&self.tmp_file)?;
+        }
+        Ok(())
+    }
 }
 
 fn check_root() -> io::Result<()> {
@@ -65,7 +145,7 @@ fn check_root() -> io::Result<()> {
 
 fn check_device(device: &str) -> io::Result<()> {
     if !Path::new(device).exists() {
-        eprintln!("{} {} {}", 
+        eprintln!("{} {} {}",
             "Error: Device".red(),
             device.blue(),
             "doesn't exist!".yellow());
@@ -76,7 +156,7 @@ fn check_device(device: &str) -> io::Result<()> {
 
 fn check_output_dir(dir: &Path) -> io::Result<()> {
     if !dir.exists() || !dir.is_dir() {
-        eprintln!("{} {} {}", 
+        eprintln!("{} {} {}",
             "Error: Directory".red(),
             dir.to_string_lossy().blue(),
             "doesn't exist!".yellow());
@@ -88,7 +168,7 @@ fn check_output_dir(dir: &Path) -> io::Result<()> {
 fn check_mount(device: &str) -> io::Result<()> {
     let mtab = fs::read_to_string("/etc/mtab")?;
     if mtab.lines().any(|line| line.contains(device)) {
-        eprintln!("{} {} {}", 
+        eprintln!("{} {} {}",
             "Error:".red(),
             device.blue(),
             "is mounted! Please unmount first.".yellow());
@@ -107,6 +187,30 @@ fn debug_command_output(cmd: &mut Command) -> io::Result<()> {
     Ok(())
 }
 
+fn extract_restored_files(output: &[u8]) -> Vec<String> {
+    let output_str = String::from_utf8_lossy(output);
+    let mut files = Vec::new();
+
+    for line in output_str.lines() {
+        if line.contains("Restoring") {
+            if let Some(path) = line.split_whitespace().nth(1) {
+                files.push(path.to_string());
+            }
+        }
+    }
+
+    files.sort();
+    files.dedup();
+    files
+}
+
+fn process_and_display_files(output: &[u8]) -> io::Result<()> {
+    for file in extract_restored_files(output) {
+        println!("{}", file);
+    }
+    Ok(())
+}
+
 fn list_files(device: &str, depth: u8, ctx: &RecoveryContext) -> io::Result<()> {
     println!("Listing recoverable files at depth {}...", depth);
 
@@ -127,26 +231,6 @@ fn list_files(device: &str, depth: u8, ctx: &RecoveryContext) -> io::Result<()>
     Ok(())
 }
 
-fn process_and_display_files(output: &[u8]) -> io::Result<()> {
-    let output_str = String::from_utf8_lossy(output);
-    let mut files = Vec::new();
-
-    for line in output_str.lines() {
-        if line.contains("Restoring") {
-            if let Some(path) = line.split_whitespace().nth(1) {
-                files.push(path.to_string());
-            }
-        }
-    }
-
-    files.sort();
-    files.dedup();
-    for file in files {
-        println!("{}", file);
-    }
-    Ok(())
-}
-
 fn generate_roots(device: &str, depth: u8, ctx: &RecoveryContext) -> io::Result<Vec<String>> {
     let args = if depth == 2 {
         println!("{}", "Note: Level 2 search may take longer and produce more results".yellow());
@@ -184,7 +268,7 @@ fn generate_roots(device: &str, depth: u8, ctx: &RecoveryContext) -> io::Result<
 
     roots.sort_by(|a, b| b.parse::<u64>().unwrap_or(0).cmp(&a.parse::<u64>().unwrap_or(0)));
     fs::write(&ctx.roots_file, roots.join("\n"))?;
-    
+
     Ok(roots)
 }
 
@@ -203,17 +287,11 @@ fn build_regex(paths: &[String]) -> String {
     patterns.join("|")
 }
 
-fn perform_recovery(device: &str, output_dir: &Path, paths: &[String], depth: u8, dry_run: bool, ctx: &RecoveryContext) -> io::Result<()> {
+fn perform_recovery(device: &str, output_dir: &Path, paths: &[String], depth: u8, ctx: &RecoveryContext) -> io::Result<()> {
     let regex = build_regex(paths);
-    
-    if dry_run {
-        println!("Performing dry run at depth {}...", depth);
-        perform_dry_run(device, &regex, depth, ctx)?;
-        return Ok(());
-    }
 
     println!("Starting recovery at depth {}...", depth);
-    
+
     if depth == 0 {
         let mut cmd = Command::new("btrfs");
         cmd.args(["restore", "-ivv", "--path-regex", &regex, device, output_dir.to_str().unwrap()]);
@@ -233,19 +311,19 @@ fn perform_recovery(device: &str, output_dir: &Path, paths: &[String], depth: u8
                 "--path-regex", &regex, device,
                 output_dir.to_str().unwrap()
             ]);
-            
+
             if let Err(e) = debug_command_output(&mut cmd) {
                 println!("{}", format!("Warning: Recovery from root {} failed: {}", root, e).yellow());
                 continue;
             }
-            
+
             remove_empty_files(output_dir)?;
         }
     }
 
     remove_empty_files(output_dir)?;
     print_recovery_summary(output_dir)?;
-    
+
     Ok(())
 }
 
@@ -266,7 +344,7 @@ fn perform_dry_run(device: &str, regex: &str, depth: u8, ctx: &RecoveryContext)
             process_and_display_files(&output.stderr)?;
         }
     }
-    
+
     Ok(())
 }
 
@@ -299,7 +377,7 @@ fn print_recovery_summary(dir: &Path) -> io::Result<()> {
         .map(String::from)
         .collect();
 
-    println!("\n{} {} {}", 
+    println!("\n{} {} {}",
         "Recovery completed:".green(),
         files.len().to_string().blue(),
         "files recovered");
@@ -314,39 +392,112 @@ fn print_recovery_summary(dir: &Path) -> io::Result<()> {
     Ok(())
 }
 
-fn main() -> io::Result<()> {
-    let args = Args::parse();
-    let ctx = RecoveryContext::new();
+fn mount_device(device: &str, mountpoint: &Path) -> io::Result<()> {
+    println!("Mounting {} read-only at {}...", device, mountpoint.to_string_lossy());
 
-    check_root()?;
-    check_device(&args.device)?;
-    check_output_dir(&args.output)?;
-    check_mount(&args.device)?;
+    let status = Command::new("mount")
+        .args(["-o", "ro,recovery", device, mountpoint.to_str().unwrap()])
+        .status()?;
+
+    if !status.success() {
+        eprintln!("{}", "Error: Failed to mount device".red());
+        exit(1);
+    }
+
+    println!("{}", "Mounted successfully. Remember to unmount when done.".green());
+    Ok(())
+}
+
+fn build_catalog(device: &str, depth: u8, output: &Path, ctx: &RecoveryContext) -> io::Result<()> {
+    println!("Cataloging recoverable files at depth {}...", depth);
 
-    if let Some(list_depth) = args.list {
-        list_files(&args.device, list_depth, &ctx)?;
+    let mut files = Vec::new();
+    if depth == 0 {
+        let cmd_output = Command::new("btrfs")
+            .args(["restore", "-Divv", "--path-regex", "^/.*$", device, "/"])
+            .output()?;
+        files.extend(extract_restored_files(&cmd_output.stderr));
     } else {
-        if args.paths.is_empty() {
-            eprintln!("{}", "Error: At least one path must be specified with -p/--path".red());
-            exit(1);
+        let roots = generate_roots(device, depth, ctx)?;
+        for root in roots {
+            let cmd_output = Command::new("btrfs")
+                .args(["restore", "-t", &root, "-Divv", "--path-regex", "^/.*$", device, "/"])
+                .output()?;
+            files.extend(extract_restored_files(&cmd_output.stderr));
         }
-        perform_recovery(
-            &args.device,
-            &args.output,
-            &args.paths,
-            args.level,
-            args.dry_run,
-            &ctx
-        )?;
     }
 
-    // Cleanup
-    if Path::new(&ctx.roots_file).exists() {
-        fs::remove_file(&ctx.roots_file)?;
-    }
-    if Path::new(&ctx.tmp_file).exists() {
-        fs::remove_file(&ctx.tmp_file)?;
-    }
+    files.sort();
+    files.dedup();
+    fs::write(output, files.join("\n"))?;
+
+    println!("{} {} {}",
+        "Catalog written to".green(),
+        output.to_string_lossy().blue(),
+        format!("({} files)", files.len()));
 
     Ok(())
 }
+
+fn run_list(args: ListArgs) -> io::Result<()> {
+    check_root()?;
+    check_device(&args.device.device)?;
+    check_mount(&args.device.device)?;
+
+    let ctx = RecoveryContext::new();
+    list_files(&args.device.device, args.level, &ctx)?;
+    ctx.cleanup()
+}
+
+fn run_recover(args: RecoverArgs) -> io::Result<()> {
+    check_root()?;
+    check_device(&args.device.device)?;
+    check_output_dir(&args.output)?;
+    check_mount(&args.device.device)?;
+
+    let ctx = RecoveryContext::new();
+    perform_recovery(&args.device.device, &args.output, &args.paths, args.level, &ctx)?;
+    ctx.cleanup()
+}
+
+fn run_dry_run(args: DryRunArgs) -> io::Result<()> {
+    check_root()?;
+    check_device(&args.device.device)?;
+    check_mount(&args.device.device)?;
+
+    let ctx = RecoveryContext::new();
+    let regex = build_regex(&args.paths);
+    perform_dry_run(&args.device.device, &regex, args.level, &ctx)?;
+    ctx.cleanup()
+}
+
+fn run_mount(args: MountArgs) -> io::Result<()> {
+    check_root()?;
+    check_device(&args.device.device)?;
+    check_output_dir(&args.mountpoint)?;
+    check_mount(&args.device.device)?;
+
+    mount_device(&args.device.device, &args.mountpoint)
+}
+
+fn run_catalog(args: CatalogArgs) -> io::Result<()> {
+    check_root()?;
+    check_device(&args.device.device)?;
+    check_mount(&args.device.device)?;
+
+    let ctx = RecoveryContext::new();
+    build_catalog(&args.device.device, args.level, &args.output, &ctx)?;
+    ctx.cleanup()
+}
+
+fn main() -> io::Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Commands::List(args) => run_list(args),
+        Commands::Recover(args) => run_recover(args),
+        Commands::DryRun(args) => run_dry_run(args),
+        Commands::Mount(args) => run_mount(args),
+        Commands::Catalog(args) => run_catalog(args),
+    }
+}